@@ -1,8 +1,206 @@
+use std::alloc::Layout;
 use std::env;
 use std::io::{self, Result};
 
 // Iteration 2: Added error handling
 
+/// Why an allocation couldn't be satisfied, with enough detail to log or retry.
+#[derive(Debug)]
+enum AllocFailure {
+    /// `size` would overflow `isize::MAX` bytes once multiplied out into a `Layout`.
+    CapacityOverflow,
+    /// The allocator returned null for this exact layout.
+    AllocError { layout: Layout },
+}
+
+impl std::fmt::Display for AllocFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AllocFailure::CapacityOverflow => write!(f, "capacity overflow"),
+            AllocFailure::AllocError { layout } => write!(
+                f,
+                "allocation of {} bytes (align {}) failed",
+                layout.size(),
+                layout.align()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AllocFailure {}
+
+/// Lowest-level allocation primitive the buffer/arena types are built on, so
+/// they can be pointed at something other than the global heap (a
+/// fixed-capacity pool, a tracking allocator in tests, etc).
+trait RawAllocator {
+    fn alloc(&self, layout: Layout) -> *mut u8;
+    fn dealloc(&self, ptr: *mut u8, layout: Layout);
+    fn realloc(&self, ptr: *mut u8, old: Layout, new_size: usize) -> *mut u8;
+}
+
+/// The default `RawAllocator`: a zero-sized type that forwards straight to
+/// `std::alloc`, so call sites that don't care keep working unchanged.
+#[derive(Default)]
+struct Global;
+
+impl RawAllocator for Global {
+    fn alloc(&self, layout: Layout) -> *mut u8 {
+        unsafe { std::alloc::alloc(layout) }
+    }
+
+    fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { std::alloc::dealloc(ptr, layout) }
+    }
+
+    fn realloc(&self, ptr: *mut u8, old: Layout, new_size: usize) -> *mut u8 {
+        unsafe { std::alloc::realloc(ptr, old, new_size) }
+    }
+}
+
+/// A growable byte buffer that manages its own allocation instead of going
+/// through `String`, so repeated writes reuse one backing allocation rather
+/// than reallocating per call. Generic over `RawAllocator` so it isn't tied
+/// to the global heap.
+struct Buffer<A: RawAllocator = Global> {
+    data: *mut u8,
+    len: usize,
+    capacity: usize,
+    alloc: A,
+}
+
+impl<A: RawAllocator + Default> Buffer<A> {
+    fn new() -> Self {
+        Buffer {
+            data: std::ptr::NonNull::dangling().as_ptr(),
+            len: 0,
+            capacity: 0,
+            alloc: A::default(),
+        }
+    }
+
+    fn with_capacity(n: usize) -> Self {
+        let mut buffer = Self::new();
+        if n > 0 {
+            buffer.reserve(n);
+        }
+        buffer
+    }
+}
+
+impl<A: RawAllocator> Buffer<A> {
+    fn layout_for(capacity: usize) -> Layout {
+        Layout::array::<u8>(capacity).expect("capacity overflow")
+    }
+
+    /// Safety: every byte in `0..len` was written by `push_str`, which only
+    /// ever appends valid UTF-8, so the live region is always valid UTF-8.
+    fn as_str(&self) -> &str {
+        unsafe {
+            let bytes = std::slice::from_raw_parts(self.data, self.len);
+            std::str::from_utf8_unchecked(bytes)
+        }
+    }
+
+    /// Grows the backing allocation to hold at least `additional` more bytes,
+    /// doubling capacity each time it needs to grow (amortized O(1) pushes).
+    fn reserve(&mut self, additional: usize) {
+        let required = self.len + additional;
+        if required <= self.capacity {
+            return;
+        }
+        let new_capacity = self.capacity.max(1).max(required).next_power_of_two();
+        let new_layout = Self::layout_for(new_capacity);
+        let new_data = if self.capacity == 0 {
+            self.alloc.alloc(new_layout)
+        } else {
+            let old_layout = Self::layout_for(self.capacity);
+            self.alloc.realloc(self.data, old_layout, new_layout.size())
+        };
+        if new_data.is_null() {
+            std::alloc::handle_alloc_error(new_layout);
+        }
+        self.data = new_data;
+        self.capacity = new_capacity;
+    }
+
+    fn push_str(&mut self, s: &str) {
+        let bytes = s.as_bytes();
+        self.reserve(bytes.len());
+        unsafe {
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), self.data.add(self.len), bytes.len());
+        }
+        self.len += bytes.len();
+    }
+}
+
+impl<A: RawAllocator> Drop for Buffer<A> {
+    fn drop(&mut self) {
+        if self.capacity != 0 {
+            self.alloc.dealloc(self.data, Self::layout_for(self.capacity));
+        }
+    }
+}
+
+/// A bump allocator: one chunk is allocated up front and `alloc_bytes` hands
+/// out sub-slices of it by advancing an offset, so repeated short-lived
+/// buffers don't each pay for their own alloc/dealloc. Generic over
+/// `RawAllocator` like `Buffer`; swapping `A` doesn't relax the `&mut self`
+/// discipline `alloc_bytes` needs to hand out non-aliased slices.
+struct Arena<A: RawAllocator = Global> {
+    data: *mut u8,
+    capacity: usize,
+    offset: usize,
+    layout: Layout,
+    alloc: A,
+}
+
+impl<A: RawAllocator + Default> Arena<A> {
+    fn with_capacity(bytes: usize) -> Self {
+        let layout = Layout::array::<u8>(bytes).expect("capacity overflow");
+        let alloc = A::default();
+        let data = alloc.alloc(layout);
+        if data.is_null() {
+            std::alloc::handle_alloc_error(layout);
+        }
+        Arena {
+            data,
+            capacity: bytes,
+            offset: 0,
+            layout,
+            alloc,
+        }
+    }
+}
+
+impl<A: RawAllocator> Arena<A> {
+    /// Returns `n` fresh bytes bumped off the front of the chunk, or `None`
+    /// once the chunk is exhausted — it never falls back to a fresh allocation.
+    /// Takes `&mut self`, not `&self`: handing out `&mut [u8]` from a shared
+    /// reference would let two calls alias the same bytes.
+    fn alloc_bytes(&mut self, n: usize) -> Option<&mut [u8]> {
+        let start = self.offset;
+        let end = start.checked_add(n)?;
+        if end > self.capacity {
+            return None;
+        }
+        self.offset = end;
+        unsafe { Some(std::slice::from_raw_parts_mut(self.data.add(start), n)) }
+    }
+
+    /// Reclaims every sub-slice handed out so far in one step, without
+    /// freeing the backing chunk. Takes `&mut self` so the borrow checker
+    /// guarantees no previously returned slice is still alive.
+    fn reset(&mut self) {
+        self.offset = 0;
+    }
+}
+
+impl<A: RawAllocator> Drop for Arena<A> {
+    fn drop(&mut self) {
+        self.alloc.dealloc(self.data, self.layout);
+    }
+}
+
 fn safe_copy(dest: &mut [u8], src: &[u8]) -> Result<()> {
     if src.len() > dest.len() {
         return Err(io::Error::new(
@@ -14,6 +212,15 @@ fn safe_copy(dest: &mut [u8], src: &[u8]) -> Result<()> {
     Ok(())
 }
 
+/// Like `safe_copy`, but the destination comes from `arena` instead of a
+/// caller-supplied slice. The returned slice's lifetime is tied to `arena`'s
+/// exclusive borrow, so it cannot outlive a `reset`.
+fn safe_copy_in<'a, A: RawAllocator>(arena: &'a mut Arena<A>, src: &[u8]) -> Option<&'a mut [u8]> {
+    let dest = arena.alloc_bytes(src.len())?;
+    dest.copy_from_slice(src);
+    Some(dest)
+}
+
 fn safe_operation() -> Result<()> {
     let data = String::from("Safe data");
     println!("{}", data);
@@ -21,10 +228,54 @@ fn safe_operation() -> Result<()> {
 }
 
 fn create_buffer() -> Result<Vec<u8>> {
-    Ok(vec![0; 256])
+    create_buffer_in(&Global).map_err(|e| io::Error::new(io::ErrorKind::OutOfMemory, e.to_string()))
+}
+
+/// Like `create_buffer`, but requests the allocation through `alloc`, so a
+/// fixed-capacity or tracking allocator can reject it without touching call
+/// sites that stick with `Global`. The returned `Vec` itself is a copy that
+/// lives on the global heap.
+fn create_buffer_in<A: RawAllocator>(
+    alloc: &A,
+) -> std::result::Result<Vec<u8>, AllocFailure> {
+    const LEN: usize = 256;
+    let layout = Layout::array::<u8>(LEN).map_err(|_| AllocFailure::CapacityOverflow)?;
+    let ptr = alloc.alloc(layout);
+    if ptr.is_null() {
+        return Err(AllocFailure::AllocError { layout });
+    }
+    // The request goes through `alloc` so a fixed-capacity or tracking
+    // allocator can reject it; the bytes are then copied into a normal
+    // heap-owned `Vec` rather than handing the raw pointer to `Vec` itself,
+    // since `Vec` always frees through the global allocator on drop.
+    let buffer = unsafe {
+        ptr.write_bytes(0, LEN);
+        std::slice::from_raw_parts(ptr, LEN).to_vec()
+    };
+    alloc.dealloc(ptr, layout);
+    Ok(buffer)
+}
+
+/// Like `create_buffer`, but never aborts: a `size` that overflows `isize::MAX`
+/// bytes or an allocator that can't satisfy the request comes back as an
+/// `AllocFailure` instead of panicking.
+fn try_create_buffer(size: usize) -> std::result::Result<Vec<u8>, AllocFailure> {
+    let layout = Layout::array::<u8>(size).map_err(|_| AllocFailure::CapacityOverflow)?;
+    let mut buffer = Vec::new();
+    buffer
+        .try_reserve_exact(size)
+        .map_err(|_| AllocFailure::AllocError { layout })?;
+    buffer.resize(size, 0);
+    Ok(buffer)
 }
 
 fn safe_allocation(size: usize) -> Result<()> {
+    safe_allocation_in(&Global, size)
+}
+
+/// Like `safe_allocation`, but routes the allocation through `alloc` instead
+/// of the global heap.
+fn safe_allocation_in<A: RawAllocator>(alloc: &A, size: usize) -> Result<()> {
     if size == 0 {
         return Err(io::Error::new(
             io::ErrorKind::InvalidInput,
@@ -37,12 +288,38 @@ fn safe_allocation(size: usize) -> Result<()> {
             "Size too large, potential overflow"
         ));
     }
-    let _buffer = vec![0u8; size];
+    let layout = Layout::array::<u8>(size).map_err(|_| {
+        io::Error::new(io::ErrorKind::InvalidInput, "Size too large, potential overflow")
+    })?;
+    let ptr = alloc.alloc(layout);
+    if ptr.is_null() {
+        return Err(io::Error::new(
+            io::ErrorKind::OutOfMemory,
+            AllocFailure::AllocError { layout }.to_string(),
+        ));
+    }
+    alloc.dealloc(ptr, layout);
     Ok(())
 }
 
+/// Fallible counterpart to `safe_allocation`: surfaces the `Layout` that
+/// couldn't be satisfied instead of letting the allocator abort the process.
+fn try_safe_allocation(size: usize) -> std::result::Result<(), AllocFailure> {
+    let layout = Layout::array::<u8>(size).map_err(|_| AllocFailure::CapacityOverflow)?;
+    let mut buffer: Vec<u8> = Vec::new();
+    buffer
+        .try_reserve_exact(size)
+        .map_err(|_| AllocFailure::AllocError { layout })?;
+    Ok(())
+}
+
+// Note: there is no `print_user_input_safe` in this file to rewrite alongside
+// `print_safe` — that name only exists in test-start-workflow/vulnerable.rs,
+// a separate demo variant. Only `print_safe` is touched here.
 fn print_safe(input: &str) -> Result<()> {
-    println!("{}", input);
+    let mut buffer = Buffer::<Global>::with_capacity(input.len());
+    buffer.push_str(input);
+    println!("{}", buffer.as_str());
     Ok(())
 }
 
@@ -53,12 +330,23 @@ fn main() -> Result<()> {
         let mut buffer = [0u8; 10];
         safe_copy(&mut buffer, args[1].as_bytes())?;
         print_safe(&args[1])?;
+
+        let mut arena = Arena::<Global>::with_capacity(64);
+        if let Some(copied) = safe_copy_in(&mut arena, args[1].as_bytes()) {
+            print_safe(std::str::from_utf8(copied).unwrap_or(""))?;
+        }
+        arena.reset();
     }
-    
+
     safe_operation()?;
     let _data = create_buffer()?;
     safe_allocation(1000000000)?;
-    
+
+    let _data = try_create_buffer(1000000000)
+        .map_err(|e| io::Error::new(io::ErrorKind::OutOfMemory, e.to_string()))?;
+    try_safe_allocation(1000000000)
+        .map_err(|e| io::Error::new(io::ErrorKind::OutOfMemory, e.to_string()))?;
+
     println!("Done");
     Ok(())
 }